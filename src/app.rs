@@ -0,0 +1,31 @@
+use winit::{application::ApplicationHandler, event::WindowEvent, event_loop::ActiveEventLoop, window::WindowId};
+
+use crate::client::Client;
+
+pub struct ClientApp<'a> {
+    client: Option<Client<'a>>,
+}
+
+impl ClientApp<'_> {
+    pub fn new() -> Self {
+        Self { client: None }
+    }
+}
+
+impl ApplicationHandler for ClientApp<'_> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.client.is_none() {
+            self.client = Some(Client::new(event_loop));
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+        let Some(client) = &mut self.client else { return };
+        client.window_event(event_loop, event);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(client) = &mut self.client else { return };
+        client.update(event_loop);
+    }
+}
@@ -0,0 +1,113 @@
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    fn new() -> Self {
+        Self {
+            view_proj: glam::Mat4::IDENTITY.to_cols_array_2d(),
+        }
+    }
+
+    fn update(&mut self, eye: glam::Vec3, target: glam::Vec3, up: glam::Vec3, aspect: f32) {
+        let view = glam::Mat4::look_at_rh(eye, target, up);
+        let proj = glam::Mat4::perspective_rh(45f32.to_radians(), aspect, 0.1, 100.0);
+        self.view_proj = (proj * view).to_cols_array_2d();
+    }
+}
+
+/// Owns the view-projection matrix and the uniform buffer / bind group it's
+/// uploaded through. `step` advances the orbit angle; `upload` recomputes
+/// the matrix from the interpolated eye position and writes it to the GPU
+/// buffer.
+pub struct Camera {
+    eye: glam::Vec3,
+    prev_eye: glam::Vec3,
+    target: glam::Vec3,
+    up: glam::Vec3,
+    aspect: f32,
+    angle: f32,
+    uniform: CameraUniform,
+    buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Camera {
+    pub fn new(device: &wgpu::Device, aspect: f32) -> Self {
+        let eye = glam::Vec3::new(2.0, 1.0, 0.0);
+        let target = glam::Vec3::ZERO;
+        let up = glam::Vec3::Y;
+
+        let mut uniform = CameraUniform::new();
+        uniform.update(eye, target, up, aspect);
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Camera Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            eye,
+            prev_eye: eye,
+            target,
+            up,
+            aspect,
+            angle: 0.0,
+            uniform,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn resize(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+
+    /// Advances the orbit by one fixed simulation step.
+    pub fn step(&mut self, dt: f32) {
+        self.prev_eye = self.eye;
+        self.angle += dt;
+        self.eye = glam::Vec3::new(self.angle.cos() * 2.0, 1.0, self.angle.sin() * 2.0);
+    }
+
+    /// Uploads the view-projection matrix for the current render, linearly
+    /// interpolating between the last two simulation steps by `alpha` so
+    /// motion stays smooth even when the render rate doesn't match the
+    /// fixed simulation rate.
+    pub fn upload(&mut self, queue: &wgpu::Queue, alpha: f32) {
+        let eye = self.prev_eye.lerp(self.eye, alpha);
+        self.uniform.update(eye, self.target, self.up, self.aspect);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+}
@@ -1,15 +1,55 @@
 use std::{
-    sync::Arc,
+    sync::{atomic::AtomicU32, Arc},
     time::{Duration, Instant},
 };
 
-use wgpu::Color;
+use wgpu::{util::DeviceExt, Color};
 use winit::{
     dpi::PhysicalSize,
-    event::WindowEvent,
-    window::{Window, WindowAttributes},
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::ControlFlow,
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Fullscreen, Window, WindowAttributes},
 };
 
+use crate::{
+    camera::Camera,
+    profiler::GpuProfiler,
+    renderer::{Phase, RenderPass, Renderer},
+    vertex::Vertex,
+};
+
+const VERTICES: &[Vertex] = &[
+    Vertex { position: [0.0, 0.5, 0.0], color: [1.0, 0.0, 0.0] },
+    Vertex { position: [-0.5, -0.5, 0.0], color: [0.0, 1.0, 0.0] },
+    Vertex { position: [0.5, -0.5, 0.0], color: [0.0, 0.0, 1.0] },
+];
+
+/// Caps how much real time a single `update()` call will turn into
+/// simulation steps, so a long stall (window drag, breakpoint) doesn't
+/// spiral into catching up hundreds of steps at once.
+const MAX_CATCHUP_SECS: f32 = 0.25;
+
+/// Draw attempts (presented or not) to make before revealing the window,
+/// so the very first
+/// (uninitialized swapchain) frame never flashes white on startup.
+const FRAMES_BEFORE_VISIBLE: u32 = 3;
+
+/// Picks the lowest-latency present mode the surface actually supports,
+/// falling back to `Fifo` (guaranteed to always be supported) so the crate
+/// never has to fall back to the driver's `AutoVsync` guess.
+fn preferred_present_mode(supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    const PREFERENCE: [wgpu::PresentMode; 3] = [
+        wgpu::PresentMode::Mailbox,
+        wgpu::PresentMode::Immediate,
+        wgpu::PresentMode::Fifo,
+    ];
+    PREFERENCE
+        .into_iter()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(wgpu::PresentMode::Fifo)
+}
+
 pub struct Client<'a> {
     window: Arc<Window>,
     exit: bool,
@@ -19,16 +59,114 @@ pub struct Client<'a> {
     surface: wgpu::Surface<'a>,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    encoder: wgpu::CommandEncoder,
     config: wgpu::SurfaceConfiguration,
-    color: u32,
+    renderer: Renderer,
+    frame_index: u32,
+    camera: Camera,
+    profiler: GpuProfiler,
+    accumulator: f32,
+    alpha: f32,
+    presented_frames: u32,
+    fullscreen: bool,
+    supported_present_modes: Vec<wgpu::PresentMode>,
+}
+
+/// Clears the frame to a cycling color. Stands in for real background
+/// geometry until the render graph grows dedicated passes.
+struct ClearPass {
+    color: AtomicU32,
+}
+
+impl RenderPass for ClearPass {
+    fn encode(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+    ) -> wgpu::CommandBuffer {
+        let idx = self.color.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % 4;
+        let c = match idx {
+            0 => Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 },
+            1 => Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 },
+            2 => Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 },
+            _ => Color { r: 1.0, g: 0.0, b: 1.0, a: 1.0 },
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Clear Pass Encoder"),
+        });
+
+        let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Clear Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(c),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+        drop(render_pass);
+
+        encoder.finish()
+    }
+}
+
+/// Draws the scene's geometry on top of whatever the background phase left
+/// in the view, using the shared camera bind group for its view-projection
+/// matrix.
+struct GeometryPass {
+    pipeline: wgpu::RenderPipeline,
+    camera_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+}
+
+impl RenderPass for GeometryPass {
+    fn encode(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+    ) -> wgpu::CommandBuffer {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Geometry Pass Encoder"),
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Geometry Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes,
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.draw(0..3, 0..1);
+        }
+
+        encoder.finish()
+    }
 }
 
 impl Client<'_> {
     pub fn new(event_loop: &winit::event_loop::ActiveEventLoop) -> Self {
         let window = Arc::new(
             event_loop
-                .create_window(WindowAttributes::default())
+                .create_window(WindowAttributes::default().with_visible(false))
                 .expect("Failed to create window"),
         );
 
@@ -84,13 +222,15 @@ impl Client<'_> {
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
 
+        let supported_present_modes = surface_caps.present_modes.clone();
+
         // create surface config
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::AutoVsync,
+            present_mode: preferred_present_mode(&supported_present_modes),
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -98,88 +238,271 @@ impl Client<'_> {
 
         surface.configure(&device, &config);
 
+        let aspect = config.width as f32 / config.height.max(1) as f32;
+        let camera = Camera::new(&device, aspect);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&camera.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::layout()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let mut renderer = Renderer::new();
+        renderer.register(
+            Phase::Background,
+            Box::new(ClearPass {
+                color: AtomicU32::new(0),
+            }),
+        );
+        renderer.register(
+            Phase::Opaque,
+            Box::new(GeometryPass {
+                pipeline,
+                camera_bind_group: camera.bind_group.clone(),
+                vertex_buffer,
+            }),
+        );
+
+        let profiler = GpuProfiler::new(&device, &queue, renderer.pass_count(), renderer.frames_in_flight());
+
         Self {
             window,
             exit: false,
             prev_update: Instant::now(),
             frame_target: Instant::now(),
             frame_time: Duration::from_secs_f32(1.0 / 60.0),
-            encoder: Self::create_encoder(&device),
             device,
             queue,
             surface,
             config,
-            color: 0,
+            renderer,
+            frame_index: 0,
+            camera,
+            profiler,
+            accumulator: 0.0,
+            alpha: 0.0,
+            presented_frames: 0,
+            fullscreen: false,
+            supported_present_modes,
         }
     }
 
-    fn create_encoder(device: &wgpu::Device) -> wgpu::CommandEncoder {
-        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Render Encoder"),
-        })
-    }
-
+    /// Advances the simulation by whole `frame_time` increments to catch up
+    /// with real elapsed time, then schedules the next tick. Leftover time
+    /// becomes `alpha`, the fraction of a step `draw` interpolates by.
+    ///
+    /// `about_to_wait` calls this on every wake, not just the ones caused by
+    /// `frame_target` elapsing (a window event can wake the loop early), so
+    /// this only does work — and only re-requests a redraw — once `now` has
+    /// actually reached `frame_target`. Otherwise it just re-asserts the
+    /// existing `WaitUntil` and returns, letting the loop go back to sleep.
     pub fn update(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         let now = Instant::now();
-        let dt = now - self.prev_update;
+
+        if now < self.frame_target {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(self.frame_target));
+            if self.exit {
+                event_loop.exit();
+            }
+            return;
+        }
+
+        let elapsed = (now - self.prev_update).as_secs_f32().min(MAX_CATCHUP_SECS);
         self.prev_update = now;
 
+        self.accumulator += elapsed;
+
+        let step = self.frame_time.as_secs_f32();
+        while self.accumulator >= step {
+            self.step(step);
+            self.accumulator -= step;
+        }
+        self.alpha = self.accumulator / step;
+
+        self.frame_target = now + self.frame_time;
+        event_loop.set_control_flow(ControlFlow::WaitUntil(self.frame_target));
+        self.window.request_redraw();
+
         if self.exit {
             event_loop.exit();
         }
     }
 
-    pub fn draw(&mut self) {
-        let mut encoder = std::mem::replace(&mut self.encoder, Self::create_encoder(&self.device));
-        let output = self.surface.get_current_texture().unwrap();
+    fn step(&mut self, dt: f32) {
+        self.camera.step(dt);
+    }
+
+    pub fn draw(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) -> Result<(), wgpu::SurfaceError> {
+        let result = self.try_draw(event_loop);
+
+        // Counts the attempt, not just a successful present: surface errors
+        // are plausible on the first few hidden-window startup frames
+        // (before the compositor has sized it), and if those never counted
+        // toward `FRAMES_BEFORE_VISIBLE` the window could stay hidden
+        // forever. Checked after the attempt (rather than before) so a
+        // successful attempt's own frame is already on screen by the time
+        // it's the one that crosses the threshold.
+        if self.presented_frames < FRAMES_BEFORE_VISIBLE {
+            self.presented_frames += 1;
+            if self.presented_frames == FRAMES_BEFORE_VISIBLE {
+                self.window.set_visible(true);
+            }
+        }
+
+        result
+    }
+
+    fn try_draw(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) -> Result<(), wgpu::SurfaceError> {
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(err @ (wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)) => {
+                self.surface.configure(&self.device, &self.config);
+                return Err(err);
+            }
+            Err(err @ wgpu::SurfaceError::OutOfMemory) => {
+                event_loop.exit();
+                return Err(err);
+            }
+            Err(err @ wgpu::SurfaceError::Timeout) => return Err(err),
+            Err(err) => return Err(err),
+        };
+
+        self.camera.upload(&self.queue, self.alpha);
+
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let c = match self.color {
-            0 => Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 },
-            1 => Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 },
-            2 => Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 },
-            _ => Color { r: 1.0, g: 0.0, b: 1.0, a: 1.0 },
-        };
+        self.renderer.render(
+            &self.device,
+            &self.queue,
+            &view,
+            Some(&self.profiler),
+            self.frame_index,
+        );
+        output.present();
 
-        self.color = (self.color + 1) % 3;
+        self.frame_index = (self.frame_index + 1) % self.renderer.frames_in_flight();
 
-        let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(c),
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
-        });
-        drop(render_pass);
+        Ok(())
+    }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+    /// The most recent GPU time, in milliseconds, for each registered
+    /// render pass. Lags a few frames behind `draw` since the readback is
+    /// asynchronous.
+    pub fn pass_timings_ms(&self) -> Vec<f32> {
+        self.profiler.timings_ms()
     }
 
-    pub fn window_event(&mut self, event: WindowEvent) {
+    pub fn window_event(&mut self, event_loop: &winit::event_loop::ActiveEventLoop, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => self.exit = true,
             WindowEvent::Resized(size) => self.resize(size),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F11),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => self.toggle_fullscreen(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F10),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => self.toggle_vsync(),
             WindowEvent::RedrawRequested => {
-                self.draw();
-                self.window.request_redraw();
+                // Lost/Outdated are already handled (surface reconfigured)
+                // by the time they reach here; Timeout just drops the frame.
+                // The next redraw is requested from `update`, on its own
+                // `WaitUntil` schedule, so this doesn't spin the loop.
+                let _ = self.draw(event_loop);
             }
             _ => (),
         }
     }
 
+    fn toggle_fullscreen(&mut self) {
+        self.fullscreen = !self.fullscreen;
+        let mode = self.fullscreen.then_some(Fullscreen::Borderless(None));
+        self.window.set_fullscreen(mode);
+    }
+
+    /// Switches the surface to `mode` and reconfigures it live. Returns
+    /// `false` without changing anything if the surface doesn't support it.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> bool {
+        if !self.supported_present_modes.contains(&mode) {
+            return false;
+        }
+
+        self.config.present_mode = mode;
+        self.surface.configure(&self.device, &self.config);
+        true
+    }
+
+    /// Cycles between vsync-on (`Fifo`) and the fastest uncapped mode the
+    /// surface supports, for benchmarking frame pacing without a recompile.
+    fn toggle_vsync(&mut self) {
+        let target = if self.config.present_mode == wgpu::PresentMode::Fifo {
+            [wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate]
+                .into_iter()
+                .find(|mode| self.supported_present_modes.contains(mode))
+                .unwrap_or(wgpu::PresentMode::Fifo)
+        } else {
+            wgpu::PresentMode::Fifo
+        };
+        self.set_present_mode(target);
+    }
+
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+
         self.config.width = size.width;
         self.config.height = size.height;
         self.surface.configure(&self.device, &self.config);
+        self.camera.resize(size.width as f32 / size.height.max(1) as f32);
     }
 }
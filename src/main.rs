@@ -1,7 +1,11 @@
 #![allow(clippy::type_complexity)]
 
 mod app;
+mod camera;
 mod client;
+mod profiler;
+mod renderer;
+mod vertex;
 
 use app::ClientApp;
 use winit::event_loop::EventLoop;
@@ -0,0 +1,162 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+/// Query set, resolve/staging buffers, and in-flight map state for a single
+/// frame-in-flight slot. Keeping one of these per slot (rather than one
+/// shared set) is what lets `resolve`/`poll` for frame N's queries proceed
+/// while frame N-1's readback is still pending.
+struct Slot {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    staging_buffer: wgpu::Buffer,
+    pending: AtomicBool,
+    map_result: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+}
+
+impl Slot {
+    fn new(device: &wgpu::Device, index: u32, max_passes: u32) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some(&format!("GPU Profiler Query Set {index}")),
+            ty: wgpu::QueryType::Timestamp,
+            count: max_passes * 2,
+        });
+
+        let buffer_size = max_passes as u64 * 2 * std::mem::size_of::<u64>() as u64;
+
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("GPU Profiler Resolve Buffer {index}")),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("GPU Profiler Staging Buffer {index}")),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query_set,
+            resolve_buffer,
+            staging_buffer,
+            pending: AtomicBool::new(false),
+            map_result: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+/// Resolves the GPU timestamp queries written by each render pass into
+/// milliseconds. Buffer mapping is asynchronous, so a frame's timings
+/// aren't available until `poll` is called again on a later frame.
+///
+/// Holds one `Slot` per frame-in-flight, round-robined by the same
+/// `frame_index` the `Renderer` uses, so one frame's pending async readback
+/// never blocks the next frame's queries from being recorded.
+pub struct GpuProfiler {
+    slots: Vec<Slot>,
+    pass_count: u32,
+    period_ns: f32,
+    timings_ms: Mutex<Vec<f32>>,
+}
+
+impl GpuProfiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, max_passes: u32, frames_in_flight: u32) -> Self {
+        let slots = (0..frames_in_flight)
+            .map(|index| Slot::new(device, index, max_passes))
+            .collect();
+
+        Self {
+            slots,
+            pass_count: max_passes,
+            period_ns: queue.get_timestamp_period(),
+            timings_ms: Mutex::new(vec![0.0; max_passes as usize]),
+        }
+    }
+
+    /// Timestamp write targets for the render pass recorded at `pass_index`
+    /// within frame `frame_index`'s slot.
+    pub fn timestamp_writes(&self, frame_index: u32, pass_index: u32) -> wgpu::RenderPassTimestampWrites {
+        let slot = &self.slots[frame_index as usize];
+        wgpu::RenderPassTimestampWrites {
+            query_set: &slot.query_set,
+            beginning_of_pass_write_index: Some(pass_index * 2),
+            end_of_pass_write_index: Some(pass_index * 2 + 1),
+        }
+    }
+
+    /// Resolves frame `frame_index`'s queries and kicks off an async map of
+    /// its staging buffer. No-op while that slot's previous map is still
+    /// pending.
+    pub fn resolve(&self, device: &wgpu::Device, queue: &wgpu::Queue, frame_index: u32) {
+        let slot = &self.slots[frame_index as usize];
+        if slot.pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("GPU Profiler Resolve Encoder"),
+        });
+        encoder.resolve_query_set(&slot.query_set, 0..self.pass_count * 2, &slot.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &slot.resolve_buffer,
+            0,
+            &slot.staging_buffer,
+            0,
+            slot.resolve_buffer.size(),
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let map_result = slot.map_result.clone();
+        slot.staging_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *map_result.lock().unwrap() = Some(result);
+            });
+    }
+
+    /// Polls frame `frame_index`'s pending map; once it completes, converts
+    /// the raw tick deltas into milliseconds per pass. Call once per frame
+    /// after `resolve`.
+    pub fn poll(&self, device: &wgpu::Device, frame_index: u32) {
+        let slot = &self.slots[frame_index as usize];
+        if !slot.pending.load(Ordering::Acquire) {
+            return;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+
+        let Some(result) = slot.map_result.lock().unwrap().take() else {
+            return;
+        };
+
+        if let Err(err) = result {
+            // Best-effort profiling: a failed map (e.g. a device-lost event)
+            // should just mean stale timings this frame, not a crashed app.
+            eprintln!("GPU profiler: failed to map staging buffer: {err}");
+            slot.pending.store(false, Ordering::Release);
+            return;
+        }
+
+        {
+            let view = slot.staging_buffer.slice(..).get_mapped_range();
+            let ticks: &[u64] = bytemuck::cast_slice(&view);
+            let mut timings = self.timings_ms.lock().unwrap();
+            for (i, timing) in timings.iter_mut().enumerate() {
+                let delta = ticks[i * 2 + 1].saturating_sub(ticks[i * 2]);
+                *timing = (delta as f32 * self.period_ns) / 1_000_000.0;
+            }
+        }
+        slot.staging_buffer.unmap();
+        slot.pending.store(false, Ordering::Release);
+    }
+
+    /// The most recent GPU time, in milliseconds, for each registered pass
+    /// (indexed the same way passes were registered with the `Renderer`).
+    pub fn timings_ms(&self) -> Vec<f32> {
+        self.timings_ms.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+use rayon::prelude::*;
+
+use crate::profiler::GpuProfiler;
+
+/// Coarse bucket a render pass is grouped into before submission. Declared
+/// in submission order: `BTreeMap` iterates variants in this order, so the
+/// derived `Ord` impl doubles as the renderer's phase schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+    Background,
+    Opaque,
+    Transparent,
+    Overlay,
+}
+
+/// A single unit of work that records its own command buffer against a
+/// shared target view. Implementors must be `Send + Sync` so passes within
+/// a phase can be encoded concurrently.
+pub trait RenderPass: Send + Sync {
+    fn encode(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+    ) -> wgpu::CommandBuffer;
+}
+
+struct RegisteredPass {
+    phase: Phase,
+    pass: Box<dyn RenderPass>,
+}
+
+/// Holds the registered render passes and drives per-frame encoding.
+/// Passes are grouped by `Phase`, phases run in their declared order, and
+/// the passes within a phase are encoded in parallel across cores before
+/// their command buffers are submitted together.
+pub struct Renderer {
+    passes: Vec<RegisteredPass>,
+    frames_in_flight: u32,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self {
+            passes: Vec::new(),
+            frames_in_flight: 2,
+        }
+    }
+
+    pub fn register(&mut self, phase: Phase, pass: Box<dyn RenderPass>) {
+        self.passes.push(RegisteredPass { phase, pass });
+    }
+
+    pub fn frames_in_flight(&self) -> u32 {
+        self.frames_in_flight
+    }
+
+    pub fn pass_count(&self) -> u32 {
+        self.passes.len() as u32
+    }
+
+    /// Encodes every registered pass and submits them to `queue` in phase
+    /// order. When `profiler` is given, each pass is assigned the timestamp
+    /// query pair matching its registration index so GPU timings line up
+    /// with `profiler.timings_ms()`. `frame_index` (round-robined by the
+    /// caller over `frames_in_flight()`) selects which slot of the
+    /// profiler's per-frame-in-flight query/readback resources this frame
+    /// uses, so one frame's pending async readback never blocks the next.
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        profiler: Option<&GpuProfiler>,
+        frame_index: u32,
+    ) {
+        let mut by_phase: BTreeMap<Phase, Vec<(u32, &RegisteredPass)>> = BTreeMap::new();
+        for (i, pass) in self.passes.iter().enumerate() {
+            by_phase.entry(pass.phase).or_default().push((i as u32, pass));
+        }
+
+        for passes in by_phase.values() {
+            let buffers: Vec<wgpu::CommandBuffer> = passes
+                .par_iter()
+                .map(|(i, entry)| {
+                    let timestamp_writes = profiler.map(|p| p.timestamp_writes(frame_index, *i));
+                    entry.pass.encode(device, view, timestamp_writes)
+                })
+                .collect();
+            queue.submit(buffers);
+        }
+
+        if let Some(profiler) = profiler {
+            profiler.resolve(device, queue, frame_index);
+            profiler.poll(device, frame_index);
+        }
+    }
+}